@@ -2,6 +2,7 @@
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
 
 /// Daemon configuration
@@ -39,6 +40,80 @@ pub struct Config {
 
     /// Cleanup stale sessions after this many seconds
     pub session_timeout_secs: u64,
+
+    /// Address to serve Prometheus metrics and `/healthz` on (e.g.
+    /// "127.0.0.1:9090"). Disabled when `None`.
+    #[serde(default)]
+    pub metrics_addr: Option<String>,
+
+    /// Webhook endpoints notified on merge lifecycle transitions
+    #[serde(default)]
+    pub webhooks: Vec<WebhookTarget>,
+
+    /// Target average op time (ms) the shared tranquilizer (see
+    /// `worker::Tranquilizer`) paces git-I/O-heavy workers to. Set to 0 to
+    /// disable throttling.
+    #[serde(default = "default_throttle_target_ms")]
+    pub throttle_target_ms: u64,
+
+    /// Number of recent operation durations the tranquilizer averages over
+    #[serde(default = "default_throttle_window_size")]
+    pub throttle_window_size: usize,
+
+    /// How often (in seconds) to run the worktree/branch/session repair
+    /// pass automatically. `None` disables periodic repair; it can still be
+    /// triggered on demand via the IPC `Repair` command.
+    #[serde(default)]
+    pub repair_interval_secs: Option<u64>,
+}
+
+/// Default for `Config::throttle_target_ms`, also used by `#[serde(default)]`
+/// so configs written before the tranquilizer existed still deserialize.
+fn default_throttle_target_ms() -> u64 {
+    2_000
+}
+
+/// Default for `Config::throttle_window_size`, also used by
+/// `#[serde(default)]` so configs written before the tranquilizer existed
+/// still deserialize.
+fn default_throttle_window_size() -> usize {
+    10
+}
+
+/// A webhook endpoint to notify on merge lifecycle transitions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookTarget {
+    /// URL to POST the JSON event payload to
+    pub url: String,
+
+    /// Extra headers to send with each delivery (e.g. auth tokens)
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+
+    /// Event kinds to deliver; `None` means "all events"
+    #[serde(default)]
+    pub events: Option<Vec<MergeEvent>>,
+}
+
+/// Merge lifecycle transitions a webhook can subscribe to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeEvent {
+    Queued,
+    Processing,
+    Merged,
+    Conflict,
+    RetryExhausted,
+}
+
+impl WebhookTarget {
+    /// Whether this target wants to hear about `event`
+    pub fn accepts(&self, event: MergeEvent) -> bool {
+        match &self.events {
+            Some(events) => events.contains(&event),
+            None => true,
+        }
+    }
 }
 
 /// Merge strategy options
@@ -67,6 +142,11 @@ impl Default for Config {
             worktree_dir: ".worktrees".to_string(),
             preserve_worktrees: false,
             session_timeout_secs: 3600,
+            metrics_addr: None,
+            webhooks: Vec::new(),
+            throttle_target_ms: default_throttle_target_ms(),
+            throttle_window_size: default_throttle_window_size(),
+            repair_interval_secs: None,
         }
     }
 }
@@ -44,14 +44,14 @@ pub enum DaemonError {
     #[error("Rebase failed: {0}")]
     RebaseFailed(String),
 
-    #[error("Max retries exceeded for agent: {0}")]
-    MaxRetriesExceeded(String),
-
     #[error("Daemon shutdown in progress")]
     ShuttingDown,
 
     #[error("Configuration error: {0}")]
     Config(String),
+
+    #[error("Webhook delivery failed: {0}")]
+    Notification(String),
 }
 
 /// Result type alias for daemon operations
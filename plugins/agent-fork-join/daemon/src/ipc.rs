@@ -0,0 +1,211 @@
+//! Unix-socket IPC server for client commands (CLI, editor plugins, etc.)
+//!
+//! The protocol is newline-delimited JSON: each client connection sends one
+//! `IpcRequest` per line and receives one `IpcResponse` per line back.
+
+use crate::error::DaemonResult;
+use crate::queue::{EntryStatus, MergeQueue, QueueEntry};
+use crate::repair::{RepairSummary, Repairer};
+use crate::state::{EntryFilter, MergeFilter, MergeRecord, Page, StateManager};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tracing::{debug, warn};
+
+/// Requests a client can send over the IPC socket
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum IpcRequest {
+    /// Enqueue an agent branch for merging
+    Enqueue {
+        agent_id: String,
+        session_id: String,
+        branch: String,
+        worktree: PathBuf,
+        target_branch: String,
+    },
+    /// Snapshot of the current queue
+    Status,
+    /// Entries in a single status, e.g. only `Failed` or only `Processing`
+    EntriesByStatus { status: EntryStatus },
+    /// Paginated, filtered query over queue entries
+    QueryEntries {
+        #[serde(default)]
+        filter: EntryFilter,
+        limit: usize,
+        #[serde(default)]
+        cursor: Option<String>,
+    },
+    /// Paginated, filtered query over merge history
+    QueryMerges {
+        #[serde(default)]
+        filter: MergeFilter,
+        limit: usize,
+        #[serde(default)]
+        cursor: Option<String>,
+    },
+    /// Run the worktree/branch/session repair pass now, instead of waiting
+    /// for its periodic schedule (if any)
+    Repair,
+    /// Ask the daemon to shut down gracefully
+    Shutdown,
+}
+
+/// Responses the daemon sends back over the IPC socket
+#[derive(Debug, Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum IpcResponse {
+    Enqueued { id: String },
+    Status { entries: Vec<QueueEntry> },
+    Entries { entries: Vec<QueueEntry> },
+    EntriesPage { items: Vec<QueueEntry>, next_cursor: Option<String> },
+    MergesPage { items: Vec<MergeRecord>, next_cursor: Option<String> },
+    Repaired { summary: RepairSummary },
+    Ok,
+    Error { message: String },
+}
+
+/// Listens on a Unix socket and dispatches requests against the merge queue
+pub struct IpcServer {
+    socket: PathBuf,
+    listener: UnixListener,
+    queue: MergeQueue,
+    state: StateManager,
+    repairer: Repairer,
+}
+
+impl IpcServer {
+    /// Bind the IPC socket. The caller is responsible for removing any stale
+    /// socket file beforehand.
+    pub fn new(
+        socket: PathBuf,
+        queue: MergeQueue,
+        state: StateManager,
+        repairer: Repairer,
+    ) -> DaemonResult<Self> {
+        let listener = UnixListener::bind(&socket)?;
+        Ok(Self {
+            socket,
+            listener,
+            queue,
+            state,
+            repairer,
+        })
+    }
+
+    /// Accept connections forever, handling each on its own task
+    pub async fn run(self) -> DaemonResult<()> {
+        let queue = self.queue;
+        let state = self.state;
+        let repairer = self.repairer;
+        let socket = Arc::new(self.socket);
+
+        loop {
+            let (stream, _addr) = self.listener.accept().await?;
+            let queue = queue.clone();
+            let state = state.clone();
+            let repairer = repairer.clone();
+            let socket = socket.clone();
+
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(stream, queue, state, repairer).await {
+                    warn!("IPC connection on {:?} ended with error: {}", socket, e);
+                }
+            });
+        }
+    }
+}
+
+async fn handle_connection(
+    stream: UnixStream,
+    queue: MergeQueue,
+    state: StateManager,
+    repairer: Repairer,
+) -> DaemonResult<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<IpcRequest>(&line) {
+            Ok(request) => handle_request(&queue, &state, &repairer, request).await,
+            Err(e) => IpcResponse::Error {
+                message: format!("invalid request: {e}"),
+            },
+        };
+
+        let mut payload = serde_json::to_string(&response)?;
+        payload.push('\n');
+        writer.write_all(payload.as_bytes()).await?;
+    }
+
+    Ok(())
+}
+
+async fn handle_request(
+    queue: &MergeQueue,
+    state: &StateManager,
+    repairer: &Repairer,
+    request: IpcRequest,
+) -> IpcResponse {
+    match request {
+        IpcRequest::Enqueue {
+            agent_id,
+            session_id,
+            branch,
+            worktree,
+            target_branch,
+        } => {
+            debug!("IPC enqueue request for agent {}", agent_id);
+            match queue
+                .enqueue(agent_id, session_id, branch, worktree, target_branch)
+                .await
+            {
+                Ok(id) => IpcResponse::Enqueued { id: id.to_string() },
+                Err(e) => IpcResponse::Error {
+                    message: e.to_string(),
+                },
+            }
+        }
+        IpcRequest::Status => IpcResponse::Status {
+            entries: queue.snapshot().await,
+        },
+        IpcRequest::EntriesByStatus { status } => match state.load_entries_by_status(status).await {
+            Ok(entries) => IpcResponse::Entries { entries },
+            Err(e) => IpcResponse::Error {
+                message: e.to_string(),
+            },
+        },
+        IpcRequest::QueryEntries { filter, limit, cursor } => {
+            match state.query_entries(&filter, limit, cursor.as_deref()).await {
+                Ok(Page { items, next_cursor }) => IpcResponse::EntriesPage { items, next_cursor },
+                Err(e) => IpcResponse::Error {
+                    message: e.to_string(),
+                },
+            }
+        }
+        IpcRequest::QueryMerges { filter, limit, cursor } => {
+            match state.query_merges(&filter, limit, cursor.as_deref()).await {
+                Ok(Page { items, next_cursor }) => IpcResponse::MergesPage { items, next_cursor },
+                Err(e) => IpcResponse::Error {
+                    message: e.to_string(),
+                },
+            }
+        }
+        IpcRequest::Repair => match repairer.run().await {
+            Ok(summary) => IpcResponse::Repaired { summary },
+            Err(e) => IpcResponse::Error {
+                message: e.to_string(),
+            },
+        },
+        IpcRequest::Shutdown => {
+            queue.shutdown().await;
+            IpcResponse::Ok
+        }
+    }
+}
@@ -8,20 +8,28 @@ mod config;
 mod error;
 mod ipc;
 mod merger;
+mod metrics;
+mod notifier;
 mod queue;
+mod repair;
 mod state;
+mod worker;
 
 use anyhow::Result;
 use clap::Parser;
 use std::path::PathBuf;
+use std::sync::Arc;
 use tokio::signal;
 use tracing::{info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::config::Config;
 use crate::ipc::IpcServer;
+use crate::metrics::Health;
 use crate::queue::MergeQueue;
+use crate::repair::Repairer;
 use crate::state::StateManager;
+use crate::worker::{Tranquilizer, WorkerManager};
 
 /// Merge daemon for multi-agent git workflows
 #[derive(Parser, Debug)]
@@ -81,17 +89,55 @@ async fn main() -> Result<()> {
     let state_manager = StateManager::new(&args.db).await?;
     info!("State database initialized at {:?}", args.db);
 
+    // Shared pacing for every worker that does git/filesystem I/O against
+    // this repo, so a burst on one (e.g. repair) backs off the others too.
+    let tranquilizer = Arc::new(Tranquilizer::new(config.throttle_target_ms, config.throttle_window_size));
+
     // Initialize merge queue
-    let queue = MergeQueue::new(args.repo.clone(), state_manager.clone(), config.clone());
+    let queue = MergeQueue::new(
+        args.repo.clone(),
+        state_manager.clone(),
+        config.clone(),
+        tranquilizer.clone(),
+    );
+
+    metrics::init();
+    let health = Health::new();
 
     // Recover any pending merges from previous run
     let recovered = queue.recover().await?;
+    health.mark_recovered();
     if recovered > 0 {
         info!("Recovered {} pending merge(s) from previous session", recovered);
     }
 
+    // Re-attempt any webhook notifications that never got delivered
+    let redelivered = queue.redeliver_pending_notifications().await?;
+    if redelivered > 0 {
+        info!("Re-attempting {} undelivered webhook notification(s)", redelivered);
+    }
+
+    // Start the metrics/healthz listener, if configured
+    let metrics_handle = if let Some(addr) = &config.metrics_addr {
+        let addr = addr.parse()?;
+        let health = health.clone();
+        info!("Serving metrics and /healthz on {}", addr);
+        Some(tokio::spawn(async move {
+            if let Err(e) = metrics::serve(addr, health).await {
+                tracing::error!("Metrics server error: {}", e);
+            }
+        }))
+    } else {
+        None
+    };
+
     // Start IPC server
-    let server = IpcServer::new(args.socket.clone(), queue.clone(), state_manager.clone())?;
+    let repairer = Repairer::new(
+        args.repo.clone(),
+        state_manager.clone(),
+        config.clone(),
+        tranquilizer.clone(),
+    );
 
     // Remove stale socket file if it exists
     if args.socket.exists() {
@@ -99,6 +145,13 @@ async fn main() -> Result<()> {
         std::fs::remove_file(&args.socket)?;
     }
 
+    let server = IpcServer::new(
+        args.socket.clone(),
+        queue.clone(),
+        state_manager.clone(),
+        repairer.clone(),
+    )?;
+
     // Spawn the IPC server
     let server_handle = tokio::spawn(async move {
         if let Err(e) = server.run().await {
@@ -106,13 +159,23 @@ async fn main() -> Result<()> {
         }
     });
 
-    // Spawn the merge processor
-    let processor_handle = tokio::spawn({
-        let queue = queue.clone();
-        async move {
-            queue.process_loop().await;
-        }
-    });
+    // Drive the merge processor (and future background jobs) through the
+    // shared worker manager, which restarts any worker that panics
+    let workers = WorkerManager::new();
+    health.mark_processor_alive();
+    let processor_handle = workers.spawn(Arc::new(queue.clone()));
+
+    // Periodic repair is opt-in; it's always available on demand via the
+    // IPC `Repair` command regardless of this setting.
+    let repair_handle = if config.repair_interval_secs.is_some() {
+        info!(
+            "Running repair pass every {}s",
+            config.repair_interval_secs.unwrap()
+        );
+        Some(workers.spawn(Arc::new(repairer)))
+    } else {
+        None
+    };
 
     info!("Merge daemon started successfully");
     info!("Listening on: {:?}", args.socket);
@@ -124,8 +187,15 @@ async fn main() -> Result<()> {
 
     // Graceful shutdown
     queue.shutdown().await;
+    workers.shutdown();
     server_handle.abort();
     processor_handle.abort();
+    if let Some(handle) = repair_handle {
+        handle.abort();
+    }
+    if let Some(handle) = metrics_handle {
+        handle.abort();
+    }
 
     // Cleanup socket file
     if args.socket.exists() {
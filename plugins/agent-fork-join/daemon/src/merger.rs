@@ -0,0 +1,222 @@
+//! Executes the actual git merge/rebase for a queue entry
+//!
+//! Git operations are blocking, so every call here hops onto a blocking
+//! thread via `tokio::task::spawn_blocking` rather than holding up the async
+//! scheduler in `queue.rs`.
+
+use crate::config::{Config, MergeStrategy};
+use crate::error::{DaemonError, DaemonResult};
+use crate::queue::QueueEntry;
+use git2::{Repository, Signature};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex as StdMutex};
+use tokio::sync::Mutex as AsyncMutex;
+use tracing::debug;
+
+/// Outcome of attempting to merge a single queue entry
+pub enum MergeOutcome {
+    /// The merge (or rebase/squash) completed and was committed
+    Merged { commit_sha: String },
+    /// The merge could not be completed cleanly; these files conflicted
+    Conflict { files: Vec<String> },
+}
+
+/// Per-(repo, target branch) mutexes serializing the read-tip/write-ref
+/// section of a merge. The disjoint-changed-files check in `queue.rs` only
+/// guarantees two concurrently dispatched entries won't *conflict*; it says
+/// nothing about two entries racing to update the same `refs/heads/<target>`.
+/// Without this, two merges both branched off tip T0 can both commit and the
+/// second `repo.commit`/`set_target` silently clobbers the first, losing a
+/// merge. Keyed by repo path too, since a single daemon process could in
+/// principle serve more than one repository.
+static TARGET_LOCKS: Lazy<StdMutex<HashMap<(PathBuf, String), Arc<AsyncMutex<()>>>>> =
+    Lazy::new(|| StdMutex::new(HashMap::new()));
+
+fn target_lock(repo_path: &Path, target_branch: &str) -> Arc<AsyncMutex<()>> {
+    let key = (repo_path.to_path_buf(), target_branch.to_string());
+    let mut locks = TARGET_LOCKS.lock().expect("target lock registry poisoned");
+    locks
+        .entry(key)
+        .or_insert_with(|| Arc::new(AsyncMutex::new(())))
+        .clone()
+}
+
+/// Performs merges/rebases/squashes for queue entries against a repository
+pub struct Merger {
+    repo_path: PathBuf,
+    config: Config,
+}
+
+impl Merger {
+    pub fn new(repo_path: &Path, config: &Config) -> Self {
+        Self {
+            repo_path: repo_path.to_path_buf(),
+            config: config.clone(),
+        }
+    }
+
+    /// Merge `entry.branch` into `entry.target_branch` using the configured
+    /// strategy, off the async runtime.
+    ///
+    /// Holds the per-target-branch lock for the duration of the git work so
+    /// that concurrently dispatched entries sharing a `target_branch` (the
+    /// disjoint-file check in `queue.rs` only rules out content conflicts,
+    /// not a ref-update race) serialize their read-tip/write-ref sections
+    /// instead of racing to update `refs/heads/<target_branch>`.
+    pub async fn merge(&self, entry: &QueueEntry) -> DaemonResult<MergeOutcome> {
+        let repo_path = self.repo_path.clone();
+        let config = self.config.clone();
+        let entry = entry.clone();
+        let lock = target_lock(&repo_path, &entry.target_branch);
+        let _guard = lock.lock().await;
+
+        tokio::task::spawn_blocking(move || Self::merge_blocking(&repo_path, &config, &entry))
+            .await
+            .map_err(|e| DaemonError::Worktree(format!("merge task panicked: {e}")))?
+    }
+
+    fn merge_blocking(
+        repo_path: &Path,
+        config: &Config,
+        entry: &QueueEntry,
+    ) -> DaemonResult<MergeOutcome> {
+        let repo = Repository::open(repo_path)?;
+
+        match config.merge_strategy {
+            MergeStrategy::Merge => Self::do_merge(&repo, entry),
+            MergeStrategy::Rebase => Self::do_rebase(&repo, entry),
+            MergeStrategy::Squash => Self::do_squash(&repo, entry),
+        }
+    }
+
+    fn do_merge(repo: &Repository, entry: &QueueEntry) -> DaemonResult<MergeOutcome> {
+        let target_ref = repo.find_branch(&entry.target_branch, git2::BranchType::Local)?;
+        let branch_ref = repo.find_branch(&entry.branch, git2::BranchType::Local)?;
+
+        let target_commit = target_ref.get().peel_to_commit()?;
+        let branch_commit = branch_ref.get().peel_to_commit()?;
+
+        let mut index = repo.merge_commits(&target_commit, &branch_commit, None)?;
+
+        if index.has_conflicts() {
+            return Ok(MergeOutcome::Conflict {
+                files: conflicted_paths(&index),
+            });
+        }
+
+        let tree_oid = index.write_tree_to(repo)?;
+        let tree = repo.find_tree(tree_oid)?;
+        let signature = Signature::now("merge-daemon", "merge-daemon@localhost")?;
+
+        let commit_oid = repo.commit(
+            Some(&format!("refs/heads/{}", entry.target_branch)),
+            &signature,
+            &signature,
+            &format!("Merge agent branch '{}' into {}", entry.branch, entry.target_branch),
+            &tree,
+            &[&target_commit, &branch_commit],
+        )?;
+
+        debug!("Merged {} into {} as {}", entry.branch, entry.target_branch, commit_oid);
+        Ok(MergeOutcome::Merged {
+            commit_sha: commit_oid.to_string(),
+        })
+    }
+
+    fn do_rebase(repo: &Repository, entry: &QueueEntry) -> DaemonResult<MergeOutcome> {
+        let target_ref = repo.find_branch(&entry.target_branch, git2::BranchType::Local)?;
+        let branch_ref = repo.find_branch(&entry.branch, git2::BranchType::Local)?;
+
+        let target_commit = target_ref.get().peel_to_commit()?;
+        let branch_commit = branch_ref.get().peel_to_commit()?;
+        let upstream = repo.find_annotated_commit(target_commit.id())?;
+        let onto = upstream.clone();
+        let branch = repo.find_annotated_commit(branch_commit.id())?;
+
+        let mut rebase = repo.rebase(Some(&branch), Some(&upstream), Some(&onto), None)?;
+        let signature = Signature::now("merge-daemon", "merge-daemon@localhost")?;
+
+        let mut last_commit = target_commit.id();
+        while let Some(op) = rebase.next() {
+            op.map_err(DaemonError::Git)?;
+
+            let index = rebase.inner_index()?;
+            if index.has_conflicts() {
+                let files = conflicted_paths(&index);
+                rebase.abort()?;
+                return Ok(MergeOutcome::Conflict { files });
+            }
+
+            match rebase.commit(None, &signature, None) {
+                Ok(oid) => last_commit = oid,
+                Err(e) if e.code() == git2::ErrorCode::Applied => {}
+                Err(e) => {
+                    rebase.abort()?;
+                    return Err(DaemonError::RebaseFailed(e.to_string()));
+                }
+            }
+        }
+
+        rebase.finish(Some(&signature))?;
+        repo.find_object(last_commit, None)?; // sanity-check the final tip resolves
+
+        let branch_name = entry.target_branch.clone();
+        let mut target_ref = repo.find_reference(&format!("refs/heads/{branch_name}"))?;
+        target_ref.set_target(last_commit, "rebase: fast-forward onto rebased branch")?;
+
+        debug!("Rebased {} onto {} at {}", entry.branch, entry.target_branch, last_commit);
+        Ok(MergeOutcome::Merged {
+            commit_sha: last_commit.to_string(),
+        })
+    }
+
+    fn do_squash(repo: &Repository, entry: &QueueEntry) -> DaemonResult<MergeOutcome> {
+        let target_ref = repo.find_branch(&entry.target_branch, git2::BranchType::Local)?;
+        let branch_ref = repo.find_branch(&entry.branch, git2::BranchType::Local)?;
+
+        let target_commit = target_ref.get().peel_to_commit()?;
+        let branch_commit = branch_ref.get().peel_to_commit()?;
+
+        let mut index = repo.merge_commits(&target_commit, &branch_commit, None)?;
+        if index.has_conflicts() {
+            return Ok(MergeOutcome::Conflict {
+                files: conflicted_paths(&index),
+            });
+        }
+
+        let tree_oid = index.write_tree_to(repo)?;
+        let tree = repo.find_tree(tree_oid)?;
+        let signature = Signature::now("merge-daemon", "merge-daemon@localhost")?;
+
+        let commit_oid = repo.commit(
+            Some(&format!("refs/heads/{}", entry.target_branch)),
+            &signature,
+            &signature,
+            &format!("Squash agent branch '{}' into {}", entry.branch, entry.target_branch),
+            &tree,
+            &[&target_commit],
+        )?;
+
+        debug!("Squashed {} into {} as {}", entry.branch, entry.target_branch, commit_oid);
+        Ok(MergeOutcome::Merged {
+            commit_sha: commit_oid.to_string(),
+        })
+    }
+}
+
+fn conflicted_paths(index: &git2::Index) -> Vec<String> {
+    index
+        .conflicts()
+        .into_iter()
+        .flatten()
+        .filter_map(|c| c.ok())
+        .filter_map(|c| {
+            c.our
+                .or(c.their)
+                .or(c.ancestor)
+                .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+        })
+        .collect()
+}
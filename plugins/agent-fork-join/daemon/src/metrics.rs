@@ -0,0 +1,170 @@
+//! Prometheus metrics and liveness endpoint
+//!
+//! Metrics are served in Prometheus text-exposition format over a small
+//! hand-rolled HTTP listener (the daemon has no other reason to depend on a
+//! full web framework), alongside a `/healthz` endpoint that reports whether
+//! the merge processor loop is running and whether startup recovery
+//! completed.
+
+use once_cell::sync::Lazy;
+use prometheus::{Encoder, Histogram, HistogramOpts, IntCounter, IntGauge, Registry, TextEncoder};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::warn;
+
+/// Registry all daemon metrics are registered into
+pub static REGISTRY: Lazy<Registry> = Lazy::new(Registry::new);
+
+/// Number of entries currently sitting in the queue
+pub static QUEUE_DEPTH: Lazy<IntGauge> = Lazy::new(|| {
+    let gauge = IntGauge::new("merge_daemon_queue_depth", "Number of entries currently queued").unwrap();
+    REGISTRY.register(Box::new(gauge.clone())).expect("register queue depth gauge");
+    gauge
+});
+
+/// Merges that completed successfully
+pub static MERGES_SUCCEEDED: Lazy<IntCounter> = Lazy::new(|| {
+    let counter = IntCounter::new("merge_daemon_merges_succeeded_total", "Merges completed successfully").unwrap();
+    REGISTRY.register(Box::new(counter.clone())).expect("register merges succeeded counter");
+    counter
+});
+
+/// Merges that exhausted their retry budget
+pub static MERGES_FAILED: Lazy<IntCounter> = Lazy::new(|| {
+    let counter = IntCounter::new("merge_daemon_merges_failed_total", "Merges that exhausted their retry budget").unwrap();
+    REGISTRY.register(Box::new(counter.clone())).expect("register merges failed counter");
+    counter
+});
+
+/// Merge attempts that hit a conflict
+pub static MERGES_CONFLICTED: Lazy<IntCounter> = Lazy::new(|| {
+    let counter = IntCounter::new("merge_daemon_merges_conflicted_total", "Merge attempts that hit a conflict").unwrap();
+    REGISTRY.register(Box::new(counter.clone())).expect("register merges conflicted counter");
+    counter
+});
+
+/// Number of times a conflicted or failed merge was requeued for retry
+pub static REBASE_RETRIES: Lazy<IntCounter> = Lazy::new(|| {
+    let counter = IntCounter::new("merge_daemon_rebase_retries_total", "Entries requeued for another merge attempt").unwrap();
+    REGISTRY.register(Box::new(counter.clone())).expect("register rebase retries counter");
+    counter
+});
+
+/// Wall-clock time of a single merge/rebase/squash attempt
+pub static MERGE_LATENCY: Lazy<Histogram> = Lazy::new(|| {
+    let histogram = Histogram::with_opts(HistogramOpts::new(
+        "merge_daemon_merge_latency_seconds",
+        "Wall-clock time of a single merge attempt",
+    ))
+    .unwrap();
+    REGISTRY.register(Box::new(histogram.clone())).expect("register merge latency histogram");
+    histogram
+});
+
+/// Time an entry spent queued before its merge attempt started
+pub static QUEUE_WAIT: Lazy<Histogram> = Lazy::new(|| {
+    let histogram = Histogram::with_opts(HistogramOpts::new(
+        "merge_daemon_queue_wait_seconds",
+        "Time an entry spent queued before its merge attempt started",
+    ))
+    .unwrap();
+    REGISTRY.register(Box::new(histogram.clone())).expect("register queue wait histogram");
+    histogram
+});
+
+/// Force registration of every metric up front, so `/metrics` reports each
+/// series at zero rather than omitting it until first touched.
+pub fn init() {
+    Lazy::force(&QUEUE_DEPTH);
+    Lazy::force(&MERGES_SUCCEEDED);
+    Lazy::force(&MERGES_FAILED);
+    Lazy::force(&MERGES_CONFLICTED);
+    Lazy::force(&REBASE_RETRIES);
+    Lazy::force(&MERGE_LATENCY);
+    Lazy::force(&QUEUE_WAIT);
+}
+
+/// Liveness state shared between the processor loop, recovery, and the
+/// `/healthz` endpoint.
+#[derive(Clone, Default)]
+pub struct Health {
+    processor_alive: Arc<AtomicBool>,
+    recovered: Arc<AtomicBool>,
+}
+
+impl Health {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mark_processor_alive(&self) {
+        self.processor_alive.store(true, Ordering::SeqCst);
+    }
+
+    pub fn mark_recovered(&self) {
+        self.recovered.store(true, Ordering::SeqCst);
+    }
+
+    fn is_ready(&self) -> bool {
+        self.processor_alive.load(Ordering::SeqCst) && self.recovered.load(Ordering::SeqCst)
+    }
+}
+
+/// Serve `/metrics` and `/healthz` forever on `addr`
+pub async fn serve(addr: SocketAddr, health: Health) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let health = health.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, health).await {
+                warn!("metrics connection error: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, health: Health) -> std::io::Result<()> {
+    let mut buf = [0u8; 1024];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let path = request
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .unwrap_or("/");
+
+    let (status, content_type, body) = match path {
+        "/metrics" => {
+            let encoder = TextEncoder::new();
+            let metric_families = REGISTRY.gather();
+            let mut buffer = Vec::new();
+            if let Err(e) = encoder.encode(&metric_families, &mut buffer) {
+                warn!("failed to encode metrics: {}", e);
+            }
+            ("200 OK", "text/plain; version=0.0.4", buffer)
+        }
+        "/healthz" if health.is_ready() => (
+            "200 OK",
+            "application/json",
+            br#"{"status":"ok"}"#.to_vec(),
+        ),
+        "/healthz" => (
+            "503 Service Unavailable",
+            "application/json",
+            br#"{"status":"not_ready"}"#.to_vec(),
+        ),
+        _ => ("404 Not Found", "text/plain", b"not found".to_vec()),
+    };
+
+    let header = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(header.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.flush().await
+}
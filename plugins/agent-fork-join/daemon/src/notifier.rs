@@ -0,0 +1,167 @@
+//! Webhook notifications for merge lifecycle events
+//!
+//! `MergeQueue` hands a [`NotificationPayload`] to [`Notifier::notify`] on
+//! every queued/processing/merged/conflict/retry-exhausted transition. Each
+//! matching webhook target is delivered to on its own task with bounded
+//! retries and exponential backoff, so a slow or unreachable endpoint never
+//! blocks the merge loop. Undelivered notifications are persisted so a
+//! restart can pick up where it left off, giving at-least-once delivery.
+
+use crate::config::{Config, MergeEvent, WebhookTarget};
+use crate::error::{DaemonError, DaemonResult};
+use crate::state::StateManager;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{debug, error, warn};
+
+const MAX_DELIVERY_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// JSON payload delivered to a webhook on a merge lifecycle transition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationPayload {
+    pub agent_id: String,
+    pub session_id: String,
+    pub branch: String,
+    pub target_branch: String,
+    pub status: MergeEvent,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_sha: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conflict_files: Option<Vec<String>>,
+}
+
+/// Delivers merge lifecycle events to configured webhook targets
+#[derive(Clone)]
+pub struct Notifier {
+    client: reqwest::Client,
+    targets: Vec<WebhookTarget>,
+    state: StateManager,
+}
+
+impl Notifier {
+    pub fn new(config: &Config, state: StateManager) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            targets: config.webhooks.clone(),
+            state,
+        }
+    }
+
+    /// Fire `payload` at every target subscribed to `payload.status`.
+    /// Delivery happens on background tasks; this never blocks the caller.
+    pub fn notify(&self, payload: NotificationPayload) {
+        for target in &self.targets {
+            if !target.accepts(payload.status) {
+                continue;
+            }
+
+            let client = self.client.clone();
+            let target = target.clone();
+            let payload = payload.clone();
+            let state = self.state.clone();
+
+            tokio::spawn(async move {
+                let id = match state.save_pending_notification(&target.url, &payload).await {
+                    Ok(id) => id,
+                    Err(e) => {
+                        error!("Failed to persist notification before delivery: {}", e);
+                        return;
+                    }
+                };
+                Self::deliver_with_retry(&client, &target, &payload, &state, &id).await;
+            });
+        }
+    }
+
+    /// Re-attempt any notifications left undelivered by a previous run,
+    /// called alongside [`crate::queue::MergeQueue::recover`] on startup.
+    pub async fn redeliver_pending(&self) -> DaemonResult<usize> {
+        let pending = self.state.load_pending_notifications().await?;
+        let mut count = 0;
+
+        for record in pending {
+            let Some(target) = self.targets.iter().find(|t| t.url == record.url).cloned() else {
+                // The webhook was removed from config since this notification
+                // was queued; drop it rather than retry forever.
+                self.state.delete_pending_notification(&record.id).await?;
+                continue;
+            };
+
+            count += 1;
+            let client = self.client.clone();
+            let state = self.state.clone();
+            let payload = record.payload;
+            let id = record.id;
+
+            tokio::spawn(async move {
+                Self::deliver_with_retry(&client, &target, &payload, &state, &id).await;
+            });
+        }
+
+        Ok(count)
+    }
+
+    async fn deliver_with_retry(
+        client: &reqwest::Client,
+        target: &WebhookTarget,
+        payload: &NotificationPayload,
+        state: &StateManager,
+        id: &str,
+    ) {
+        let mut backoff = INITIAL_BACKOFF;
+
+        for attempt in 1..=MAX_DELIVERY_ATTEMPTS {
+            match Self::send(client, target, payload).await {
+                Ok(()) => {
+                    if let Err(e) = state.delete_pending_notification(id).await {
+                        error!("Failed to clear delivered notification {}: {}", id, e);
+                    }
+                    debug!("Delivered {:?} webhook to {} (attempt {})", payload.status, target.url, attempt);
+                    return;
+                }
+                Err(e) => {
+                    warn!(
+                        "Webhook delivery to {} failed (attempt {}/{}): {}",
+                        target.url, attempt, MAX_DELIVERY_ATTEMPTS, e
+                    );
+                    if attempt < MAX_DELIVERY_ATTEMPTS {
+                        tokio::time::sleep(backoff).await;
+                        backoff *= 2;
+                    }
+                }
+            }
+        }
+
+        error!(
+            "Giving up on webhook {} for agent {} after {} attempts; will retry on next daemon startup",
+            target.url, payload.agent_id, MAX_DELIVERY_ATTEMPTS
+        );
+    }
+
+    async fn send(
+        client: &reqwest::Client,
+        target: &WebhookTarget,
+        payload: &NotificationPayload,
+    ) -> DaemonResult<()> {
+        let mut request = client.post(&target.url).json(payload);
+        for (name, value) in &target.headers {
+            request = request.header(name, value);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| DaemonError::Notification(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(DaemonError::Notification(format!(
+                "{} responded with {}",
+                target.url,
+                response.status()
+            )));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,600 @@
+//! Merge queue: conflict-aware scheduling of agent branches onto a target branch
+//!
+//! Entries are dispatched in FIFO order, but the scheduler may run several
+//! non-conflicting merges at once (bounded by `max_concurrent_merges`). Two
+//! entries conflict when their changed-file sets intersect; an entry whose
+//! changed-file set could not be computed is treated as conflicting with
+//! everything, so it always runs alone.
+
+use crate::config::{Config, MergeEvent};
+use crate::error::{DaemonError, DaemonResult};
+use crate::merger::{Merger, MergeOutcome};
+use crate::metrics;
+use crate::notifier::{NotificationPayload, Notifier};
+use crate::state::StateManager;
+use crate::worker::{Tranquilizer, WorkOutcome, Worker};
+use chrono::{DateTime, Utc};
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Mutex, Notify};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Status of a queue entry as it moves through the merge pipeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EntryStatus {
+    Pending,
+    Processing,
+    Merged,
+    Failed,
+    Conflicted,
+}
+
+/// A single agent branch waiting to be merged into `target_branch`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueEntry {
+    pub id: Uuid,
+    pub agent_id: String,
+    pub session_id: String,
+    pub branch: String,
+    pub worktree: PathBuf,
+    pub target_branch: String,
+    pub attempts: u32,
+    pub queued_at: DateTime<Utc>,
+    pub status: EntryStatus,
+    pub last_error: Option<String>,
+    pub conflict_files: Vec<String>,
+    /// Files changed by `branch` relative to its merge-base with `target_branch`.
+    /// `None` means the set could not be computed (e.g. a missing branch or a
+    /// diff failure) and the entry is treated as conflicting with every other
+    /// entry, to stay safe.
+    pub changed_files: Option<Vec<String>>,
+}
+
+impl QueueEntry {
+    fn new(
+        agent_id: String,
+        session_id: String,
+        branch: String,
+        worktree: PathBuf,
+        target_branch: String,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            agent_id,
+            session_id,
+            branch,
+            worktree,
+            target_branch,
+            attempts: 0,
+            queued_at: Utc::now(),
+            status: EntryStatus::Pending,
+            last_error: None,
+            conflict_files: Vec::new(),
+            changed_files: None,
+        }
+    }
+
+    /// Whether this entry and `other` touch an overlapping set of files.
+    /// A `None` changed-file set conflicts with everything.
+    fn conflicts_with(&self, other: &QueueEntry) -> bool {
+        match (&self.changed_files, &other.changed_files) {
+            (Some(a), Some(b)) => a.iter().any(|f| b.contains(f)),
+            _ => true,
+        }
+    }
+
+    fn notification(&self, status: MergeEvent, commit_sha: Option<String>) -> NotificationPayload {
+        NotificationPayload {
+            agent_id: self.agent_id.clone(),
+            session_id: self.session_id.clone(),
+            branch: self.branch.clone(),
+            target_branch: self.target_branch.clone(),
+            status,
+            commit_sha,
+            conflict_files: if self.conflict_files.is_empty() {
+                None
+            } else {
+                Some(self.conflict_files.clone())
+            },
+        }
+    }
+}
+
+/// FIFO merge queue with conflict-checked concurrent dispatch
+#[derive(Clone)]
+pub struct MergeQueue {
+    inner: Arc<QueueInner>,
+}
+
+struct QueueInner {
+    repo: PathBuf,
+    state: StateManager,
+    config: Config,
+    notifier: Notifier,
+    entries: Mutex<VecDeque<QueueEntry>>,
+    in_flight: Mutex<HashSet<Uuid>>,
+    notify: Notify,
+    shutdown: Mutex<bool>,
+    done_tx: mpsc::UnboundedSender<Uuid>,
+    done_rx: Mutex<mpsc::UnboundedReceiver<Uuid>>,
+    /// Paces merge dispatch so bursts don't peg git I/O. Shared with other
+    /// workers on the same repo (e.g. the repair pass) so pacing reflects
+    /// total git I/O pressure, not just this queue's.
+    tranquilizer: Arc<Tranquilizer>,
+}
+
+impl MergeQueue {
+    /// Create a new, empty merge queue, pacing dispatch via `tranquilizer`
+    /// (shared with other workers on the same repo).
+    pub fn new(repo: PathBuf, state: StateManager, config: Config, tranquilizer: Arc<Tranquilizer>) -> Self {
+        let (done_tx, done_rx) = mpsc::unbounded_channel();
+        let notifier = Notifier::new(&config, state.clone());
+        Self {
+            inner: Arc::new(QueueInner {
+                repo,
+                state,
+                config,
+                notifier,
+                entries: Mutex::new(VecDeque::new()),
+                in_flight: Mutex::new(HashSet::new()),
+                notify: Notify::new(),
+                shutdown: Mutex::new(false),
+                done_tx,
+                done_rx: Mutex::new(done_rx),
+                tranquilizer,
+            }),
+        }
+    }
+
+    /// Enqueue an agent branch for merging, returning its queue entry id
+    pub async fn enqueue(
+        &self,
+        agent_id: String,
+        session_id: String,
+        branch: String,
+        worktree: PathBuf,
+        target_branch: String,
+    ) -> DaemonResult<Uuid> {
+        let mut entries = self.inner.entries.lock().await;
+
+        if entries.len() >= self.inner.config.max_queue_size {
+            return Err(DaemonError::QueueFull(self.inner.config.max_queue_size));
+        }
+
+        if entries.iter().any(|e| e.agent_id == agent_id) {
+            return Err(DaemonError::AgentAlreadyQueued(agent_id));
+        }
+
+        let mut entry = QueueEntry::new(agent_id, session_id, branch, worktree, target_branch);
+        entry.changed_files = self.compute_changed_files(&entry);
+
+        self.inner.state.save_entry(&entry).await?;
+        let id = entry.id;
+        self.inner
+            .notifier
+            .notify(entry.notification(MergeEvent::Queued, None));
+        entries.push_back(entry);
+        metrics::QUEUE_DEPTH.set(entries.len() as i64);
+        drop(entries);
+
+        self.inner.notify.notify_one();
+        Ok(id)
+    }
+
+    /// Diff `entry.branch` against the merge-base with `entry.target_branch` to
+    /// find the set of files it changes. Returns `None` (conflicts-with-all)
+    /// when the diff can't be computed. Renames are not detected (no
+    /// similarity pass is run), so a renamed file, including each file under
+    /// a renamed directory, is listed under both its old and its new path
+    /// rather than making the entry conflict with everything.
+    fn compute_changed_files(&self, entry: &QueueEntry) -> Option<Vec<String>> {
+        let repo = Repository::open(&self.inner.repo).ok()?;
+
+        let branch_ref = repo
+            .find_branch(&entry.branch, git2::BranchType::Local)
+            .ok()?;
+        let target_ref = repo
+            .find_branch(&entry.target_branch, git2::BranchType::Local)
+            .ok()?;
+
+        let branch_commit = branch_ref.get().peel_to_commit().ok()?;
+        let target_commit = target_ref.get().peel_to_commit().ok()?;
+
+        let merge_base = repo
+            .merge_base(branch_commit.id(), target_commit.id())
+            .ok()?;
+        let base_commit = repo.find_commit(merge_base).ok()?;
+
+        let base_tree = base_commit.tree().ok()?;
+        let branch_tree = branch_commit.tree().ok()?;
+
+        let diff = repo
+            .diff_tree_to_tree(Some(&base_tree), Some(&branch_tree), None)
+            .ok()?;
+
+        let mut files = Vec::new();
+        for delta in diff.deltas() {
+            // Without a similarity pass git2 reports no `Renamed` deltas, so
+            // this only guards against a rename delta that lacks an old path;
+            // such a delta falls back to conflicts-with-all rather than risk a
+            // false "safe to merge concurrently".
+            if delta.status() == git2::Delta::Renamed && delta.old_file().path().is_none() {
+                return None;
+            }
+
+            if let Some(path) = delta.new_file().path().or_else(|| delta.old_file().path()) {
+                files.push(path.to_string_lossy().into_owned());
+            } else {
+                return None;
+            }
+        }
+
+        Some(files)
+    }
+
+    /// Reload pending/processing entries from the state database after a
+    /// restart, recomputing their changed-file sets since the stored value may
+    /// be stale relative to the current target tip.
+    pub async fn recover(&self) -> DaemonResult<usize> {
+        let pending = self.inner.state.load_pending_entries().await?;
+        let count = pending.len();
+
+        let mut entries = self.inner.entries.lock().await;
+        for mut entry in pending {
+            entry.status = EntryStatus::Pending;
+            entry.changed_files = self.compute_changed_files(&entry);
+            self.inner.state.save_entry(&entry).await?;
+            entries.push_back(entry);
+        }
+        metrics::QUEUE_DEPTH.set(entries.len() as i64);
+        drop(entries);
+
+        if count > 0 {
+            self.inner.notify.notify_one();
+        }
+        Ok(count)
+    }
+
+    /// Scan the queue in FIFO order and pick the earliest pending entries
+    /// whose changed-file sets are disjoint from every in-flight entry and
+    /// from each other, up to the configured concurrency limit.
+    async fn select_dispatchable(&self) -> Vec<QueueEntry> {
+        let entries = self.inner.entries.lock().await;
+        let in_flight_ids = self.inner.in_flight.lock().await;
+
+        let slots = self
+            .inner
+            .config
+            .max_concurrent_merges
+            .saturating_sub(in_flight_ids.len());
+        if slots == 0 {
+            return Vec::new();
+        }
+
+        let in_flight_entries: Vec<&QueueEntry> = entries
+            .iter()
+            .filter(|e| in_flight_ids.contains(&e.id))
+            .collect();
+
+        let mut picked: Vec<QueueEntry> = Vec::new();
+        for entry in entries.iter() {
+            if picked.len() >= slots {
+                break;
+            }
+            if entry.status != EntryStatus::Pending || in_flight_ids.contains(&entry.id) {
+                continue;
+            }
+
+            let conflicts_in_flight = in_flight_entries.iter().any(|f| entry.conflicts_with(f));
+            let conflicts_picked = picked.iter().any(|p| entry.conflicts_with(p));
+            if !conflicts_in_flight && !conflicts_picked {
+                picked.push(entry.clone());
+            }
+        }
+
+        picked
+    }
+
+    /// Mark an entry as processing and run its merge/rebase on a background
+    /// task so it doesn't block the scheduler loop.
+    async fn spawn_merge(&self, mut entry: QueueEntry) {
+        metrics::QUEUE_WAIT.observe((Utc::now() - entry.queued_at).num_milliseconds().max(0) as f64 / 1000.0);
+
+        entry.status = EntryStatus::Processing;
+        self.update_entry(&entry).await;
+        self.inner.in_flight.lock().await.insert(entry.id);
+        self.inner
+            .notifier
+            .notify(entry.notification(MergeEvent::Processing, None));
+
+        let inner = self.inner.clone();
+        tokio::spawn(async move {
+            let merger = Merger::new(&inner.repo, &inner.config);
+            let started = Instant::now();
+            let result = merger.merge(&entry).await;
+            let elapsed = started.elapsed();
+            metrics::MERGE_LATENCY.observe(elapsed.as_secs_f64());
+            inner.tranquilizer.record(elapsed).await;
+            Self::finish_merge(&inner, entry, result).await;
+        });
+    }
+
+    async fn finish_merge(
+        inner: &Arc<QueueInner>,
+        mut entry: QueueEntry,
+        result: DaemonResult<MergeOutcome>,
+    ) {
+        let id = entry.id;
+
+        match result {
+            Ok(MergeOutcome::Merged { commit_sha }) => {
+                entry.status = EntryStatus::Merged;
+                if let Err(e) = inner
+                    .state
+                    .record_merge(&entry.id, &entry.agent_id, &entry.session_id, &commit_sha)
+                    .await
+                {
+                    error!("Failed to record merge history for {}: {}", entry.id, e);
+                }
+                if let Err(e) = inner.state.delete_entry(&entry.id).await {
+                    error!("Failed to delete merged entry {}: {}", entry.id, e);
+                }
+                Self::remove_entry(inner, id).await;
+                metrics::MERGES_SUCCEEDED.inc();
+                inner
+                    .notifier
+                    .notify(entry.notification(MergeEvent::Merged, Some(commit_sha.clone())));
+                info!("Merged agent {} ({})", entry.agent_id, commit_sha);
+            }
+            Ok(MergeOutcome::Conflict { files }) => {
+                entry.attempts += 1;
+                entry.status = EntryStatus::Conflicted;
+                entry.conflict_files = files;
+                metrics::MERGES_CONFLICTED.inc();
+                inner
+                    .notifier
+                    .notify(entry.notification(MergeEvent::Conflict, None));
+                warn!(
+                    "Conflict merging agent {} (attempt {}/{})",
+                    entry.agent_id, entry.attempts, inner.config.max_retries
+                );
+                Self::requeue_or_fail(inner, entry).await;
+            }
+            Err(e) => {
+                entry.attempts += 1;
+                entry.last_error = Some(e.to_string());
+                error!(
+                    "Merge failed for agent {} (attempt {}/{}): {}",
+                    entry.agent_id, entry.attempts, inner.config.max_retries, e
+                );
+                Self::requeue_or_fail(inner, entry).await;
+            }
+        }
+
+        inner.in_flight.lock().await.remove(&id);
+        let _ = inner.done_tx.send(id);
+        inner.notify.notify_one();
+    }
+
+    async fn requeue_or_fail(inner: &Arc<QueueInner>, mut entry: QueueEntry) {
+        if entry.attempts >= inner.config.max_retries {
+            entry.status = EntryStatus::Failed;
+            entry.last_error = Some(
+                entry
+                    .last_error
+                    .unwrap_or_else(|| "max retries exceeded".to_string()),
+            );
+            metrics::MERGES_FAILED.inc();
+            inner
+                .notifier
+                .notify(entry.notification(MergeEvent::RetryExhausted, None));
+        } else {
+            entry.status = EntryStatus::Pending;
+            metrics::REBASE_RETRIES.inc();
+        }
+
+        let id = entry.id;
+        if let Err(e) = inner.state.save_entry(&entry).await {
+            error!("Failed to persist entry {}: {}", id, e);
+        }
+
+        let mut entries = inner.entries.lock().await;
+        if let Some(slot) = entries.iter_mut().find(|e| e.id == id) {
+            *slot = entry;
+        }
+    }
+
+    async fn remove_entry(inner: &Arc<QueueInner>, id: Uuid) {
+        let mut entries = inner.entries.lock().await;
+        entries.retain(|e| e.id != id);
+        metrics::QUEUE_DEPTH.set(entries.len() as i64);
+    }
+
+    async fn update_entry(&self, entry: &QueueEntry) {
+        if let Err(e) = self.inner.state.save_entry(entry).await {
+            error!("Failed to persist entry {}: {}", entry.id, e);
+        }
+        let mut entries = self.inner.entries.lock().await;
+        if let Some(slot) = entries.iter_mut().find(|e| e.id == entry.id) {
+            *slot = entry.clone();
+        }
+    }
+
+    /// Drain completion notifications and recompute the changed-file sets of
+    /// any queued entry whose merge-base is now stale because the target
+    /// branch tip moved.
+    async fn drain_completions(&self) {
+        let mut rx = self.inner.done_rx.lock().await;
+        let mut moved = false;
+        while rx.try_recv().is_ok() {
+            moved = true;
+        }
+        drop(rx);
+
+        if !moved {
+            return;
+        }
+
+        let mut entries = self.inner.entries.lock().await;
+        let stale: Vec<usize> = (0..entries.len())
+            .filter(|&i| entries[i].status == EntryStatus::Pending)
+            .collect();
+        let snapshot: Vec<QueueEntry> = stale.iter().map(|&i| entries[i].clone()).collect();
+        drop(entries);
+
+        for entry in snapshot {
+            let changed = self.compute_changed_files(&entry);
+            let mut entries = self.inner.entries.lock().await;
+            if let Some(slot) = entries.iter_mut().find(|e| e.id == entry.id) {
+                slot.changed_files = changed;
+            }
+        }
+    }
+
+    /// Signal the processor loop to stop after its current iteration
+    pub async fn shutdown(&self) {
+        *self.inner.shutdown.lock().await = true;
+        self.inner.notify.notify_waiters();
+    }
+
+    /// Current snapshot of queue entries, FIFO order
+    pub async fn snapshot(&self) -> Vec<QueueEntry> {
+        self.inner.entries.lock().await.iter().cloned().collect()
+    }
+
+    /// Re-attempt any webhook notifications left undelivered by a previous
+    /// run. Call this alongside [`Self::recover`] on startup.
+    pub async fn redeliver_pending_notifications(&self) -> DaemonResult<usize> {
+        self.inner.notifier.redeliver_pending().await
+    }
+}
+
+#[async_trait::async_trait]
+impl Worker for MergeQueue {
+    fn name(&self) -> &str {
+        "merge-processor"
+    }
+
+    /// One scheduling step: drain completions, let the tranquilizer pace
+    /// dispatch, then dispatch the next non-conflicting batch.
+    async fn work(&self) -> WorkOutcome {
+        if *self.inner.shutdown.lock().await {
+            return WorkOutcome::Done;
+        }
+
+        self.drain_completions().await;
+        self.inner.tranquilizer.throttle().await;
+
+        let dispatchable = self.select_dispatchable().await;
+        if dispatchable.is_empty() {
+            return WorkOutcome::Idle;
+        }
+
+        for entry in dispatchable {
+            self.spawn_merge(entry).await;
+        }
+        WorkOutcome::Progress
+    }
+
+    fn idle_backoff(&self) -> Duration {
+        Duration::from_millis(500)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worker::Tranquilizer;
+
+    fn entry(name: &str, target_branch: &str, changed_files: Option<Vec<&str>>) -> QueueEntry {
+        let mut e = QueueEntry::new(
+            name.to_string(),
+            "session-1".to_string(),
+            format!("agent/{name}"),
+            PathBuf::from(format!("/tmp/{name}")),
+            target_branch.to_string(),
+        );
+        e.changed_files = changed_files.map(|files| files.into_iter().map(String::from).collect());
+        e
+    }
+
+    #[test]
+    fn conflicts_with_disjoint_file_sets_does_not_conflict() {
+        let a = entry("a", "main", Some(vec!["a.rs"]));
+        let b = entry("b", "main", Some(vec!["b.rs"]));
+        assert!(!a.conflicts_with(&b));
+    }
+
+    #[test]
+    fn conflicts_with_overlapping_file_sets_conflicts() {
+        let a = entry("a", "main", Some(vec!["shared.rs"]));
+        let b = entry("b", "main", Some(vec!["shared.rs"]));
+        assert!(a.conflicts_with(&b));
+    }
+
+    #[test]
+    fn conflicts_with_unknown_changed_files_conflicts_with_everything() {
+        let a = entry("a", "main", None);
+        let b = entry("b", "main", Some(vec!["b.rs"]));
+        assert!(a.conflicts_with(&b));
+        assert!(b.conflicts_with(&a));
+    }
+
+    async fn test_queue(max_concurrent_merges: usize) -> MergeQueue {
+        let db_path = std::env::temp_dir().join(format!("afj-queue-test-{}.db", Uuid::new_v4()));
+        let state = StateManager::new(&db_path).await.expect("open state db");
+        let config = Config {
+            max_concurrent_merges,
+            ..Config::default()
+        };
+        let tranquilizer = Arc::new(Tranquilizer::new(config.throttle_target_ms, config.throttle_window_size));
+        MergeQueue::new(PathBuf::from("/tmp/afj-queue-test-repo"), state, config, tranquilizer)
+    }
+
+    /// The disjoint-changed-files check is what lets `max_concurrent_merges`
+    /// run more than one merge at once in the first place.
+    #[tokio::test]
+    async fn select_dispatchable_runs_disjoint_entries_concurrently() {
+        let queue = test_queue(2).await;
+        {
+            let mut entries = queue.inner.entries.lock().await;
+            entries.push_back(entry("a", "main", Some(vec!["a.rs"])));
+            entries.push_back(entry("b", "main", Some(vec!["b.rs"])));
+        }
+
+        let dispatchable = queue.select_dispatchable().await;
+        assert_eq!(dispatchable.len(), 2, "disjoint entries should dispatch together");
+    }
+
+    #[tokio::test]
+    async fn select_dispatchable_serializes_overlapping_entries() {
+        let queue = test_queue(2).await;
+        {
+            let mut entries = queue.inner.entries.lock().await;
+            entries.push_back(entry("a", "main", Some(vec!["shared.rs"])));
+            entries.push_back(entry("b", "main", Some(vec!["shared.rs"])));
+        }
+
+        let dispatchable = queue.select_dispatchable().await;
+        assert_eq!(dispatchable.len(), 1, "overlapping entries must not dispatch concurrently");
+        assert_eq!(dispatchable[0].agent_id, "a", "FIFO order: earliest entry wins the slot");
+    }
+
+    #[tokio::test]
+    async fn select_dispatchable_respects_max_concurrent_merges() {
+        let queue = test_queue(1).await;
+        {
+            let mut entries = queue.inner.entries.lock().await;
+            entries.push_back(entry("a", "main", Some(vec!["a.rs"])));
+            entries.push_back(entry("b", "main", Some(vec!["b.rs"])));
+        }
+
+        let dispatchable = queue.select_dispatchable().await;
+        assert_eq!(dispatchable.len(), 1, "max_concurrent_merges=1 must cap dispatch at one entry");
+    }
+}
@@ -0,0 +1,272 @@
+//! Worktree, branch, and session repair/GC pass
+//!
+//! Modeled on a storage-repair routine: anything on disk (a worktree, an
+//! agent branch) that the state database no longer references is garbage
+//! left behind by a crash and gets pruned; anything the state database
+//! expects but is stale (a session open long past `session_timeout_secs`)
+//! gets expired. Runs infrequently enough off the hot merge path that it
+//! does its git/filesystem work inline rather than via `spawn_blocking`.
+
+use crate::config::Config;
+use crate::error::DaemonResult;
+use crate::state::StateManager;
+use crate::worker::{Tranquilizer, WorkOutcome, Worker};
+use chrono::Utc;
+use git2::Repository;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Counts of what a single repair pass cleaned up
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct RepairSummary {
+    pub worktrees_pruned: usize,
+    pub branches_deleted: usize,
+    pub sessions_expired: usize,
+}
+
+/// Reconciles on-disk worktrees and git branches with the queue's state
+/// database, and expires sessions that have sat open too long.
+#[derive(Clone)]
+pub struct Repairer {
+    repo: PathBuf,
+    state: StateManager,
+    config: Config,
+    /// Shared with the merge queue so a repair pass's git/filesystem I/O
+    /// paces against (and is paced by) merge dispatch, rather than each
+    /// tracking its own window.
+    tranquilizer: Arc<Tranquilizer>,
+}
+
+impl Repairer {
+    pub fn new(repo: PathBuf, state: StateManager, config: Config, tranquilizer: Arc<Tranquilizer>) -> Self {
+        Self {
+            repo,
+            state,
+            config,
+            tranquilizer,
+        }
+    }
+
+    /// Run one repair pass, returning a summary of what was cleaned up.
+    pub async fn run(&self) -> DaemonResult<RepairSummary> {
+        self.tranquilizer.throttle().await;
+        let started = Instant::now();
+
+        let mut summary = RepairSummary::default();
+
+        self.prune_orphan_worktrees(&mut summary).await?;
+        self.expire_stale_sessions(&mut summary).await?;
+
+        self.tranquilizer.record(started.elapsed()).await;
+
+        self.state.record_repair(&summary).await?;
+        info!(
+            "Repair pass complete: {} worktree(s) pruned, {} branch(es) deleted, {} session(s) expired",
+            summary.worktrees_pruned, summary.branches_deleted, summary.sessions_expired
+        );
+
+        Ok(summary)
+    }
+
+    /// Scan `worktree_dir` for directories with no corresponding live queue
+    /// entry (a `Failed` row still counts as live — it's retry-exhausted
+    /// agent work, not a crash orphan), verify each against git2's worktree
+    /// list, and prune both the worktree and its now-unreachable agent
+    /// branch.
+    async fn prune_orphan_worktrees(&self, summary: &mut RepairSummary) -> DaemonResult<()> {
+        let worktree_root = self.repo.join(&self.config.worktree_dir);
+        if !worktree_root.is_dir() {
+            return Ok(());
+        }
+
+        // Compare by final path component rather than full-path equality:
+        // `path` is built from our own config (`repo.join(worktree_dir)`)
+        // while `worktree` strings in the state DB are whatever the
+        // enqueueing client stored (often absolute, possibly relative to a
+        // different cwd). Byte-comparing the two would misclassify a live
+        // worktree as an orphan and delete unmerged agent work out from
+        // under it.
+        let in_use: HashSet<String> = self
+            .state
+            .load_worktree_paths_in_use()
+            .await?
+            .iter()
+            .filter_map(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .collect();
+        let repo = Repository::open(&self.repo)?;
+
+        let entries = match std::fs::read_dir(&worktree_root) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Could not scan worktree dir {:?}: {}", worktree_root, e);
+                return Ok(());
+            }
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !path.is_dir() || in_use.contains(&name) {
+                continue;
+            }
+
+            // A worktree left over from a successful merge with
+            // `preserve_worktrees` on is intentional, not an orphan.
+            if self.config.preserve_worktrees {
+                continue;
+            }
+
+            self.prune_worktree(&repo, &path, &name, summary);
+        }
+
+        Ok(())
+    }
+
+    fn prune_worktree(&self, repo: &Repository, path: &Path, name: &str, summary: &mut RepairSummary) {
+        if let Ok(worktree) = repo.find_worktree(name) {
+            if let Err(e) = worktree.prune(None) {
+                warn!("Failed to prune git worktree registration for {:?}: {}", path, e);
+            }
+        }
+
+        if path.exists() {
+            if let Err(e) = std::fs::remove_dir_all(path) {
+                warn!("Failed to remove orphan worktree dir {:?}: {}", path, e);
+                return;
+            }
+        }
+
+        summary.worktrees_pruned += 1;
+        info!("Pruned orphan worktree {:?}", path);
+
+        let branch_name = format!("{}{}", self.config.agent_branch_prefix, name);
+        if let Ok(mut branch) = repo.find_branch(&branch_name, git2::BranchType::Local) {
+            if branch.delete().is_ok() {
+                summary.branches_deleted += 1;
+                info!("Deleted now-unreachable agent branch {}", branch_name);
+            }
+        }
+    }
+
+    /// Expire sessions older than `session_timeout_secs`: remove their
+    /// pending, processing and conflicted queue entries (`Failed` entries are
+    /// kept) and mark them abandoned.
+    async fn expire_stale_sessions(&self, summary: &mut RepairSummary) -> DaemonResult<()> {
+        let cutoff = Utc::now() - chrono::Duration::seconds(self.config.session_timeout_secs as i64);
+        let stale = self.state.load_active_sessions_older_than(cutoff).await?;
+
+        for session in stale {
+            let removed = self.state.delete_pending_entries_for_session(&session.id).await?;
+            self.state
+                .mark_session_abandoned(&session.id, "session_timeout_secs exceeded")
+                .await?;
+
+            summary.sessions_expired += 1;
+            warn!(
+                "Expired session {} ({} pending entr{} removed)",
+                session.id,
+                removed,
+                if removed == 1 { "y" } else { "ies" }
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Worker for Repairer {
+    fn name(&self) -> &str {
+        "repair"
+    }
+
+    async fn work(&self) -> WorkOutcome {
+        if let Err(e) = self.run().await {
+            warn!("Repair pass failed: {}", e);
+        }
+        WorkOutcome::Idle
+    }
+
+    fn idle_backoff(&self) -> Duration {
+        Duration::from_secs(self.config.repair_interval_secs.unwrap_or(3600))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queue::{EntryStatus, QueueEntry};
+    use uuid::Uuid;
+
+    async fn repairer_with_worktree(status: EntryStatus) -> (Repairer, PathBuf) {
+        let repo_dir = std::env::temp_dir().join(format!("afj-repair-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&repo_dir).expect("create repo dir");
+        Repository::init(&repo_dir).expect("init git repo");
+
+        let config = Config::default();
+        let worktree_path = repo_dir.join(&config.worktree_dir).join("agent-1");
+        std::fs::create_dir_all(&worktree_path).expect("create worktree dir");
+
+        let db_path = repo_dir.join("state.db");
+        let state = StateManager::new(&db_path).await.expect("open state db");
+
+        let entry = QueueEntry {
+            id: Uuid::new_v4(),
+            agent_id: "agent-1".to_string(),
+            session_id: "session-1".to_string(),
+            branch: "agent/agent-1".to_string(),
+            worktree: worktree_path.clone(),
+            target_branch: "main".to_string(),
+            attempts: 3,
+            queued_at: Utc::now(),
+            status,
+            last_error: Some("max retries exceeded".to_string()),
+            conflict_files: Vec::new(),
+            changed_files: None,
+        };
+        state.save_entry(&entry).await.expect("save entry");
+
+        let tranquilizer = Arc::new(Tranquilizer::new(config.throttle_target_ms, config.throttle_window_size));
+        (Repairer::new(repo_dir, state, config, tranquilizer), worktree_path)
+    }
+
+    /// A `Failed` entry's worktree is retry-exhausted, unmerged agent work
+    /// the operator likely wants to inspect, not a crash orphan, so a repair
+    /// pass must not delete it (or its agent branch) while the row exists.
+    #[tokio::test]
+    async fn repair_does_not_prune_failed_entry_worktree() {
+        let (repairer, worktree_path) = repairer_with_worktree(EntryStatus::Failed).await;
+
+        let summary = repairer.run().await.expect("run repair pass");
+
+        assert_eq!(summary.worktrees_pruned, 0);
+        assert!(worktree_path.is_dir(), "Failed entry's worktree must survive a repair pass");
+    }
+
+    /// Sanity check for the above: a worktree with no queue entry at all is
+    /// still recognized as an orphan and pruned.
+    #[tokio::test]
+    async fn repair_prunes_worktree_with_no_entry() {
+        let repo_dir = std::env::temp_dir().join(format!("afj-repair-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&repo_dir).expect("create repo dir");
+        Repository::init(&repo_dir).expect("init git repo");
+
+        let config = Config::default();
+        let worktree_path = repo_dir.join(&config.worktree_dir).join("orphan-1");
+        std::fs::create_dir_all(&worktree_path).expect("create worktree dir");
+
+        let db_path = repo_dir.join("state.db");
+        let state = StateManager::new(&db_path).await.expect("open state db");
+        let tranquilizer = Arc::new(Tranquilizer::new(config.throttle_target_ms, config.throttle_window_size));
+        let repairer = Repairer::new(repo_dir, state, config, tranquilizer);
+
+        let summary = repairer.run().await.expect("run repair pass");
+
+        assert_eq!(summary.worktrees_pruned, 1);
+        assert!(!worktree_path.exists(), "orphan worktree should be removed");
+    }
+}
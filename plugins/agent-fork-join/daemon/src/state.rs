@@ -1,8 +1,11 @@
 //! Persistent state management using SQLite
 
 use crate::error::DaemonResult;
-use crate::queue::QueueEntry;
-use rusqlite::{params, Connection};
+use crate::notifier::NotificationPayload;
+use crate::queue::{EntryStatus, QueueEntry};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, ToSql};
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -40,6 +43,7 @@ impl StateManager {
                 status TEXT NOT NULL,
                 last_error TEXT,
                 conflict_files TEXT,
+                changed_files TEXT,
                 updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
             );
 
@@ -54,6 +58,7 @@ impl StateManager {
                 original_prompt TEXT,
                 created_at TEXT NOT NULL,
                 state TEXT NOT NULL,
+                abandon_reason TEXT,
                 updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
             );
 
@@ -63,9 +68,24 @@ impl StateManager {
                 agent_id TEXT NOT NULL,
                 session_id TEXT NOT NULL,
                 commit_sha TEXT,
-                merged_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                merged_at TEXT NOT NULL,
                 FOREIGN KEY (entry_id) REFERENCES queue_entries(id)
             );
+
+            CREATE TABLE IF NOT EXISTS pending_notifications (
+                id TEXT PRIMARY KEY,
+                url TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );
+
+            CREATE TABLE IF NOT EXISTS repair_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                worktrees_pruned INTEGER NOT NULL,
+                branches_deleted INTEGER NOT NULL,
+                sessions_expired INTEGER NOT NULL,
+                ran_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );
             "#,
         )?;
 
@@ -79,12 +99,13 @@ impl StateManager {
         let conn = self.conn.lock().await;
 
         let conflict_files = serde_json::to_string(&entry.conflict_files)?;
+        let changed_files = serde_json::to_string(&entry.changed_files)?;
 
         conn.execute(
             r#"
             INSERT OR REPLACE INTO queue_entries
-            (id, agent_id, session_id, branch, worktree, target_branch, attempts, queued_at, status, last_error, conflict_files, updated_at)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, CURRENT_TIMESTAMP)
+            (id, agent_id, session_id, branch, worktree, target_branch, attempts, queued_at, status, last_error, conflict_files, changed_files, updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, CURRENT_TIMESTAMP)
             "#,
             params![
                 entry.id.to_string(),
@@ -98,6 +119,7 @@ impl StateManager {
                 serde_json::to_string(&entry.status)?,
                 entry.last_error,
                 conflict_files,
+                changed_files,
             ],
         )?;
 
@@ -124,7 +146,7 @@ impl StateManager {
 
         let mut stmt = conn.prepare(
             r#"
-            SELECT id, agent_id, session_id, branch, worktree, target_branch, attempts, queued_at, status, last_error, conflict_files
+            SELECT id, agent_id, session_id, branch, worktree, target_branch, attempts, queued_at, status, last_error, conflict_files, changed_files
             FROM queue_entries
             WHERE status IN ('"Pending"', '"Processing"')
             ORDER BY queued_at ASC
@@ -132,31 +154,190 @@ impl StateManager {
         )?;
 
         let entries = stmt
-            .query_map([], |row| {
-                let id: String = row.get(0)?;
-                let conflict_files: String = row.get(10)?;
-                let status: String = row.get(8)?;
+            .query_map([], entry_from_row)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(entries)
+    }
+
+    /// Load entries matching a single `status`, backed by `idx_queue_status`.
+    /// Useful for recovery and admin tooling that only care about e.g.
+    /// `Failed` or `Processing` entries.
+    pub async fn load_entries_by_status(&self, status: EntryStatus) -> DaemonResult<Vec<QueueEntry>> {
+        let conn = self.conn.lock().await;
+
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, agent_id, session_id, branch, worktree, target_branch, attempts, queued_at, status, last_error, conflict_files, changed_files
+            FROM queue_entries
+            WHERE status = ?1
+            ORDER BY queued_at ASC
+            "#,
+        )?;
+
+        let entries = stmt
+            .query_map(params![serde_json::to_string(&status)?], entry_from_row)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(entries)
+    }
+
+    /// Paginated, filtered query over queue entries. `cursor` is the
+    /// `next_cursor` from a previous page; pass `None` for the first page.
+    pub async fn query_entries(
+        &self,
+        filter: &EntryFilter,
+        limit: usize,
+        cursor: Option<&str>,
+    ) -> DaemonResult<Page<QueueEntry>> {
+        let conn = self.conn.lock().await;
+
+        let mut clauses = Vec::new();
+        let mut bound: Vec<Box<dyn ToSql>> = Vec::new();
 
-                Ok(QueueEntry {
-                    id: Uuid::parse_str(&id).unwrap_or_else(|_| Uuid::new_v4()),
+        if let Some(agent_id) = &filter.agent_id {
+            clauses.push("agent_id = ?".to_string());
+            bound.push(Box::new(agent_id.clone()));
+        }
+        if let Some(session_id) = &filter.session_id {
+            clauses.push("session_id = ?".to_string());
+            bound.push(Box::new(session_id.clone()));
+        }
+        if let Some(status) = &filter.status {
+            clauses.push("status = ?".to_string());
+            bound.push(Box::new(serde_json::to_string(status)?));
+        }
+        if let Some(after) = &filter.queued_after {
+            clauses.push("queued_at > ?".to_string());
+            bound.push(Box::new(after.to_rfc3339()));
+        }
+        if let Some(before) = &filter.queued_before {
+            clauses.push("queued_at < ?".to_string());
+            bound.push(Box::new(before.to_rfc3339()));
+        }
+        if let Some(cursor) = cursor {
+            let (queued_at, id) = decode_cursor(cursor)?;
+            clauses.push("(queued_at > ? OR (queued_at = ? AND id > ?))".to_string());
+            bound.push(Box::new(queued_at.clone()));
+            bound.push(Box::new(queued_at));
+            bound.push(Box::new(id));
+        }
+
+        let where_clause = if clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", clauses.join(" AND "))
+        };
+
+        let sql = format!(
+            r#"
+            SELECT id, agent_id, session_id, branch, worktree, target_branch, attempts, queued_at, status, last_error, conflict_files, changed_files
+            FROM queue_entries
+            {where_clause}
+            ORDER BY queued_at ASC, id ASC
+            LIMIT ?
+            "#
+        );
+        bound.push(Box::new((limit + 1) as i64));
+
+        let mut stmt = conn.prepare(&sql)?;
+        let param_refs: Vec<&dyn ToSql> = bound.iter().map(|p| p.as_ref()).collect();
+
+        let mut items: Vec<QueueEntry> = stmt
+            .query_map(param_refs.as_slice(), entry_from_row)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        let next_cursor = if items.len() > limit {
+            items.truncate(limit);
+            items
+                .last()
+                .map(|e| encode_cursor(&e.queued_at.to_rfc3339(), &e.id.to_string()))
+        } else {
+            None
+        };
+
+        Ok(Page { items, next_cursor })
+    }
+
+    /// Paginated, filtered query over merge history.
+    pub async fn query_merges(
+        &self,
+        filter: &MergeFilter,
+        limit: usize,
+        cursor: Option<&str>,
+    ) -> DaemonResult<Page<MergeRecord>> {
+        let conn = self.conn.lock().await;
+
+        let mut clauses = Vec::new();
+        let mut bound: Vec<Box<dyn ToSql>> = Vec::new();
+
+        if let Some(agent_id) = &filter.agent_id {
+            clauses.push("agent_id = ?".to_string());
+            bound.push(Box::new(agent_id.clone()));
+        }
+        if let Some(session_id) = &filter.session_id {
+            clauses.push("session_id = ?".to_string());
+            bound.push(Box::new(session_id.clone()));
+        }
+        if let Some(after) = &filter.merged_after {
+            clauses.push("merged_at > ?".to_string());
+            bound.push(Box::new(after.to_rfc3339()));
+        }
+        if let Some(before) = &filter.merged_before {
+            clauses.push("merged_at < ?".to_string());
+            bound.push(Box::new(before.to_rfc3339()));
+        }
+        if let Some(cursor) = cursor {
+            let id: i64 = cursor
+                .parse()
+                .map_err(|_| crate::error::DaemonError::InvalidRequest("invalid cursor".to_string()))?;
+            clauses.push("id > ?".to_string());
+            bound.push(Box::new(id));
+        }
+
+        let where_clause = if clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", clauses.join(" AND "))
+        };
+
+        let sql = format!(
+            r#"
+            SELECT id, agent_id, commit_sha, merged_at
+            FROM merge_history
+            {where_clause}
+            ORDER BY id ASC
+            LIMIT ?
+            "#
+        );
+        bound.push(Box::new((limit + 1) as i64));
+
+        let mut stmt = conn.prepare(&sql)?;
+        let param_refs: Vec<&dyn ToSql> = bound.iter().map(|p| p.as_ref()).collect();
+
+        let mut items: Vec<MergeRecord> = stmt
+            .query_map(param_refs.as_slice(), |row| {
+                Ok(MergeRecord {
+                    id: row.get(0)?,
                     agent_id: row.get(1)?,
-                    session_id: row.get(2)?,
-                    branch: row.get(3)?,
-                    worktree: std::path::PathBuf::from(row.get::<_, String>(4)?),
-                    target_branch: row.get(5)?,
-                    attempts: row.get(6)?,
-                    queued_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(7)?)
-                        .map(|dt| dt.with_timezone(&chrono::Utc))
-                        .unwrap_or_else(|_| chrono::Utc::now()),
-                    status: serde_json::from_str(&status).unwrap_or(crate::queue::EntryStatus::Pending),
-                    last_error: row.get(9)?,
-                    conflict_files: serde_json::from_str(&conflict_files).unwrap_or_default(),
+                    commit_sha: row.get(2)?,
+                    merged_at: row.get(3)?,
                 })
             })?
             .filter_map(|r| r.ok())
             .collect();
 
-        Ok(entries)
+        let next_cursor = if items.len() > limit {
+            items.truncate(limit);
+            items.last().map(|r| r.id.to_string())
+        } else {
+            None
+        };
+
+        Ok(Page { items, next_cursor })
     }
 
     /// Record a successful merge in history
@@ -171,10 +352,16 @@ impl StateManager {
 
         conn.execute(
             r#"
-            INSERT INTO merge_history (entry_id, agent_id, session_id, commit_sha)
-            VALUES (?1, ?2, ?3, ?4)
+            INSERT INTO merge_history (entry_id, agent_id, session_id, commit_sha, merged_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
             "#,
-            params![entry_id.to_string(), agent_id, session_id, commit_sha],
+            params![
+                entry_id.to_string(),
+                agent_id,
+                session_id,
+                commit_sha,
+                Utc::now().to_rfc3339(),
+            ],
         )?;
 
         Ok(())
@@ -186,7 +373,7 @@ impl StateManager {
 
         let mut stmt = conn.prepare(
             r#"
-            SELECT agent_id, commit_sha, merged_at
+            SELECT id, agent_id, commit_sha, merged_at
             FROM merge_history
             WHERE session_id = ?1
             ORDER BY merged_at ASC
@@ -196,9 +383,10 @@ impl StateManager {
         let records = stmt
             .query_map(params![session_id], |row| {
                 Ok(MergeRecord {
-                    agent_id: row.get(0)?,
-                    commit_sha: row.get(1)?,
-                    merged_at: row.get(2)?,
+                    id: row.get(0)?,
+                    agent_id: row.get(1)?,
+                    commit_sha: row.get(2)?,
+                    merged_at: row.get(3)?,
                 })
             })?
             .filter_map(|r| r.ok())
@@ -206,12 +394,324 @@ impl StateManager {
 
         Ok(records)
     }
+
+    /// Persist a webhook notification that hasn't been delivered yet,
+    /// returning its id so the caller can clear it once delivery succeeds.
+    pub async fn save_pending_notification(
+        &self,
+        url: &str,
+        payload: &NotificationPayload,
+    ) -> DaemonResult<String> {
+        let conn = self.conn.lock().await;
+        let id = Uuid::new_v4().to_string();
+
+        conn.execute(
+            "INSERT INTO pending_notifications (id, url, payload) VALUES (?1, ?2, ?3)",
+            params![id, url, serde_json::to_string(payload)?],
+        )?;
+
+        Ok(id)
+    }
+
+    /// Remove a notification once it has been delivered
+    pub async fn delete_pending_notification(&self, id: &str) -> DaemonResult<()> {
+        let conn = self.conn.lock().await;
+        conn.execute("DELETE FROM pending_notifications WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Load every notification left undelivered by a previous run
+    pub async fn load_pending_notifications(&self) -> DaemonResult<Vec<PendingNotification>> {
+        let conn = self.conn.lock().await;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, url, payload FROM pending_notifications ORDER BY created_at ASC",
+        )?;
+
+        let records = stmt
+            .query_map([], |row| {
+                let payload: String = row.get(2)?;
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, payload))
+            })?
+            .filter_map(|r| r.ok())
+            .filter_map(|(id, url, payload)| {
+                serde_json::from_str(&payload)
+                    .ok()
+                    .map(|payload| PendingNotification { id, url, payload })
+            })
+            .collect();
+
+        Ok(records)
+    }
+
+    /// Worktree paths of every entry that still has a live row in
+    /// `queue_entries` (i.e. not `Merged`, which is deleted on success). A
+    /// `Failed` entry's worktree is retry-exhausted, unmerged agent work an
+    /// operator likely wants to inspect, not a crash orphan, so it counts as
+    /// in-use here too. Used by the repair pass to decide which on-disk
+    /// worktrees are orphans.
+    pub async fn load_worktree_paths_in_use(&self) -> DaemonResult<std::collections::HashSet<std::path::PathBuf>> {
+        let conn = self.conn.lock().await;
+
+        let mut stmt = conn.prepare(
+            r#"SELECT worktree FROM queue_entries WHERE status IN ('"Pending"', '"Processing"', '"Conflicted"', '"Failed"')"#,
+        )?;
+
+        let paths = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .filter_map(|r| r.ok())
+            .map(std::path::PathBuf::from)
+            .collect();
+
+        Ok(paths)
+    }
+
+    /// Sessions that are still open (not already `abandoned`) and were
+    /// created before `cutoff`.
+    pub async fn load_active_sessions_older_than(&self, cutoff: DateTime<Utc>) -> DaemonResult<Vec<SessionRecord>> {
+        let conn = self.conn.lock().await;
+
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, feature_branch, base_branch, created_at, state
+            FROM sessions
+            WHERE state != 'abandoned' AND created_at < ?1
+            "#,
+        )?;
+
+        let sessions = stmt
+            .query_map(params![cutoff.to_rfc3339()], |row| {
+                Ok(SessionRecord {
+                    id: row.get(0)?,
+                    feature_branch: row.get(1)?,
+                    base_branch: row.get(2)?,
+                    created_at: row.get(3)?,
+                    state: row.get(4)?,
+                })
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(sessions)
+    }
+
+    /// Mark a session abandoned, recording why so an operator can tell a
+    /// timeout from a deliberate cancellation later.
+    pub async fn mark_session_abandoned(&self, session_id: &str, reason: &str) -> DaemonResult<()> {
+        let conn = self.conn.lock().await;
+
+        conn.execute(
+            "UPDATE sessions SET state = 'abandoned', abandon_reason = ?2, updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+            params![session_id, reason],
+        )?;
+
+        Ok(())
+    }
+
+    /// Remove a session's `Pending`, `Processing` and `Conflicted` queue
+    /// entries. `Failed` entries are kept. Returns how many were removed.
+    pub async fn delete_pending_entries_for_session(&self, session_id: &str) -> DaemonResult<usize> {
+        let conn = self.conn.lock().await;
+
+        let removed = conn.execute(
+            r#"DELETE FROM queue_entries WHERE session_id = ?1 AND status IN ('"Pending"', '"Processing"', '"Conflicted"')"#,
+            params![session_id],
+        )?;
+
+        Ok(removed)
+    }
+
+    /// Record the outcome of a repair pass for later auditing
+    pub async fn record_repair(&self, summary: &crate::repair::RepairSummary) -> DaemonResult<()> {
+        let conn = self.conn.lock().await;
+
+        conn.execute(
+            "INSERT INTO repair_history (worktrees_pruned, branches_deleted, sessions_expired) VALUES (?1, ?2, ?3)",
+            params![
+                summary.worktrees_pruned as i64,
+                summary.branches_deleted as i64,
+                summary.sessions_expired as i64,
+            ],
+        )?;
+
+        Ok(())
+    }
 }
 
 /// Record of a completed merge
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MergeRecord {
+    pub id: i64,
     pub agent_id: String,
     pub commit_sha: String,
     pub merged_at: String,
 }
+
+/// A session row, as loaded for the repair pass's expiry check
+#[derive(Debug, Clone)]
+pub struct SessionRecord {
+    pub id: String,
+    pub feature_branch: String,
+    pub base_branch: String,
+    pub created_at: String,
+    pub state: String,
+}
+
+/// A webhook notification that has not yet been delivered
+#[derive(Debug)]
+pub struct PendingNotification {
+    pub id: String,
+    pub url: String,
+    pub payload: NotificationPayload,
+}
+
+/// Predicate pushdown filter for [`StateManager::query_entries`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EntryFilter {
+    pub agent_id: Option<String>,
+    pub session_id: Option<String>,
+    pub status: Option<EntryStatus>,
+    pub queued_after: Option<DateTime<Utc>>,
+    pub queued_before: Option<DateTime<Utc>>,
+}
+
+/// Predicate pushdown filter for [`StateManager::query_merges`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MergeFilter {
+    pub agent_id: Option<String>,
+    pub session_id: Option<String>,
+    pub merged_after: Option<DateTime<Utc>>,
+    pub merged_before: Option<DateTime<Utc>>,
+}
+
+/// A page of results plus an opaque cursor for fetching the next page.
+/// `next_cursor` is `None` once the caller has seen the last page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+/// Shared row-to-`QueueEntry` mapping for every query that selects the
+/// standard `queue_entries` column list in order.
+fn entry_from_row(row: &rusqlite::Row) -> rusqlite::Result<QueueEntry> {
+    let id: String = row.get(0)?;
+    let conflict_files: String = row.get(10)?;
+    let changed_files: Option<String> = row.get(11)?;
+    let status: String = row.get(8)?;
+
+    Ok(QueueEntry {
+        id: Uuid::parse_str(&id).unwrap_or_else(|_| Uuid::new_v4()),
+        agent_id: row.get(1)?,
+        session_id: row.get(2)?,
+        branch: row.get(3)?,
+        worktree: std::path::PathBuf::from(row.get::<_, String>(4)?),
+        target_branch: row.get(5)?,
+        attempts: row.get(6)?,
+        queued_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(7)?)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+        status: serde_json::from_str(&status).unwrap_or(EntryStatus::Pending),
+        last_error: row.get(9)?,
+        conflict_files: serde_json::from_str(&conflict_files).unwrap_or_default(),
+        changed_files: changed_files.and_then(|s| serde_json::from_str(&s).ok()),
+    })
+}
+
+/// Encode a `(queued_at, id)` keyset position as an opaque cursor string
+fn encode_cursor(queued_at: &str, id: &str) -> String {
+    serde_json::to_string(&(queued_at, id)).unwrap_or_default()
+}
+
+/// Decode a cursor produced by [`encode_cursor`]
+fn decode_cursor(cursor: &str) -> DaemonResult<(String, String)> {
+    serde_json::from_str(cursor)
+        .map_err(|_| crate::error::DaemonError::InvalidRequest("invalid cursor".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    async fn temp_state() -> StateManager {
+        let path = std::env::temp_dir().join(format!("afj-state-test-{}.db", Uuid::new_v4()));
+        StateManager::new(&path).await.expect("open temp state db")
+    }
+
+    fn entry_at(queued_at: DateTime<Utc>, target_branch: &str) -> QueueEntry {
+        QueueEntry {
+            id: Uuid::new_v4(),
+            agent_id: "agent-1".to_string(),
+            session_id: "session-1".to_string(),
+            branch: "agent/agent-1".to_string(),
+            worktree: PathBuf::from("/tmp/agent-1"),
+            target_branch: target_branch.to_string(),
+            attempts: 0,
+            queued_at,
+            status: EntryStatus::Pending,
+            last_error: None,
+            conflict_files: Vec::new(),
+            changed_files: None,
+        }
+    }
+
+    /// `merged_at` is written via `Utc::now().to_rfc3339()` (not SQLite's
+    /// `CURRENT_TIMESTAMP`, which lacks the `T`/zone and sorts before any
+    /// rfc3339 string) so that a `merged_after` filter bound an hour in the
+    /// past actually matches a merge recorded "now".
+    #[tokio::test]
+    async fn query_merges_filters_by_merged_after() {
+        let state = temp_state().await;
+        let entry_id = Uuid::new_v4();
+        state
+            .record_merge(&entry_id, "agent-1", "session-1", "deadbeef")
+            .await
+            .expect("record merge");
+
+        let filter = MergeFilter {
+            merged_after: Some(Utc::now() - chrono::Duration::hours(1)),
+            ..Default::default()
+        };
+        let page = state
+            .query_merges(&filter, 10, None)
+            .await
+            .expect("query merges");
+
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].commit_sha, "deadbeef");
+    }
+
+    #[tokio::test]
+    async fn query_entries_paginates_with_cursor() {
+        let state = temp_state().await;
+        let base = Utc::now() - chrono::Duration::minutes(10);
+        for i in 0..3 {
+            let entry = entry_at(base + chrono::Duration::minutes(i), "main");
+            state.save_entry(&entry).await.expect("save entry");
+        }
+
+        let filter = EntryFilter::default();
+        let first_page = state
+            .query_entries(&filter, 2, None)
+            .await
+            .expect("query first page");
+        assert_eq!(first_page.items.len(), 2);
+        let cursor = first_page.next_cursor.expect("first page has a cursor");
+
+        let second_page = state
+            .query_entries(&filter, 2, Some(&cursor))
+            .await
+            .expect("query second page");
+        assert_eq!(second_page.items.len(), 1);
+        assert!(second_page.next_cursor.is_none());
+
+        let seen: std::collections::HashSet<_> = first_page
+            .items
+            .iter()
+            .chain(second_page.items.iter())
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(seen.len(), 3, "pagination must not skip or duplicate rows");
+    }
+}
@@ -0,0 +1,157 @@
+//! Generic background worker supervision
+//!
+//! Background jobs (merge processing, worktree and session repair, ...)
+//! implement [`Worker`] and are driven by a single
+//! [`WorkerManager`] so they share one shutdown signal and one
+//! panic-restart policy instead of each hand-rolling a `tokio::spawn` loop.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{debug, error, info};
+
+/// Result of a single [`Worker::work`] step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkOutcome {
+    /// Did something useful; the manager calls `work()` again immediately
+    Progress,
+    /// Nothing to do right now; the manager waits `idle_backoff()` before
+    /// calling `work()` again
+    Idle,
+    /// This worker has nothing left to do, ever; the manager stops driving it
+    Done,
+}
+
+/// A background job the [`WorkerManager`] can drive, supervise, and pace
+#[async_trait::async_trait]
+pub trait Worker: Send + Sync {
+    /// Human-readable name, used in logs
+    fn name(&self) -> &str;
+
+    /// Perform one unit of work and report what happened
+    async fn work(&self) -> WorkOutcome;
+
+    /// How long to sleep after an `Idle` step before calling `work()` again
+    fn idle_backoff(&self) -> Duration {
+        Duration::from_millis(500)
+    }
+}
+
+/// Shared pacing mechanism for workers that do bursty git/filesystem I/O.
+/// Tracks a sliding window of recent operation durations and, once their
+/// rolling average exceeds `target`, sleeps proportionally to the overshoot
+/// before letting the caller start its next operation. Originally bespoke to
+/// the merge processor; hoisted here so other workers sharing the same repo
+/// (repair, ...) can pace against the same window instead of each tracking
+/// its own, so a burst on one backs off the group rather than just itself.
+pub struct Tranquilizer {
+    target: Duration,
+    window_size: usize,
+    recent: Mutex<VecDeque<Duration>>,
+}
+
+impl Tranquilizer {
+    /// `target_ms: 0` disables throttling entirely.
+    pub fn new(target_ms: u64, window_size: usize) -> Self {
+        Self {
+            target: Duration::from_millis(target_ms),
+            window_size: window_size.max(1),
+            recent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Record a completed operation's wall-clock duration in the sliding
+    /// window.
+    pub async fn record(&self, elapsed: Duration) {
+        let mut window = self.recent.lock().await;
+        window.push_back(elapsed);
+        while window.len() > self.window_size {
+            window.pop_front();
+        }
+    }
+
+    /// If the rolling average duration exceeds `target`, sleep
+    /// proportionally to the overshoot before returning.
+    pub async fn throttle(&self) {
+        if self.target.is_zero() {
+            return;
+        }
+
+        let avg = {
+            let window = self.recent.lock().await;
+            if window.is_empty() {
+                return;
+            }
+            window.iter().sum::<Duration>() / window.len() as u32
+        };
+
+        if avg > self.target {
+            let overshoot = avg.as_secs_f64() / self.target.as_secs_f64();
+            let sleep_for = self.target.mul_f64(overshoot - 1.0);
+            debug!(
+                "Tranquilizer: avg op time {:?} exceeds budget {:?}, sleeping {:?}",
+                avg, self.target, sleep_for
+            );
+            tokio::time::sleep(sleep_for).await;
+        }
+    }
+}
+
+/// Drives registered workers on their own supervised tasks: restarts a
+/// worker whose `work()` call panics, and stops all of them on shutdown.
+#[derive(Clone, Default)]
+pub struct WorkerManager {
+    shutdown: Arc<AtomicBool>,
+}
+
+impl WorkerManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start driving `worker` on its own task, looping until shutdown or
+    /// until it reports [`WorkOutcome::Done`].
+    pub fn spawn(&self, worker: Arc<dyn Worker>) -> tokio::task::JoinHandle<()> {
+        let shutdown = self.shutdown.clone();
+
+        tokio::spawn(async move {
+            loop {
+                if shutdown.load(Ordering::SeqCst) {
+                    info!("Worker '{}' stopping for shutdown", worker.name());
+                    break;
+                }
+
+                let stepped = {
+                    let worker = worker.clone();
+                    tokio::spawn(async move { worker.work().await }).await
+                };
+
+                let outcome = match stepped {
+                    Ok(outcome) => outcome,
+                    Err(e) => {
+                        error!("Worker '{}' panicked ({}); restarting", worker.name(), e);
+                        WorkOutcome::Idle
+                    }
+                };
+
+                match outcome {
+                    WorkOutcome::Done => {
+                        info!("Worker '{}' finished", worker.name());
+                        break;
+                    }
+                    WorkOutcome::Progress => {}
+                    WorkOutcome::Idle => {
+                        tokio::time::sleep(worker.idle_backoff()).await;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Signal every driven worker's loop to stop after its current step
+    pub fn shutdown(&self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+    }
+}